@@ -1,5 +1,4 @@
-use std::io::{File, Open, Read, Append, ReadWrite, IoResult,
-    SeekSet, SeekCur};
+use std::io::{File, Open, Read, Append, ReadWrite, IoError};
 
 #[deriving(Show, PartialEq)]
 pub struct Pixel {
@@ -10,6 +9,67 @@ pub struct Pixel {
 
 pub mod consts;
 
+/// Anything that can go wrong while decoding or encoding a bitmap.
+#[deriving(Show)]
+pub enum BmpError {
+    /// A read or write against the backing file failed.
+    Io(IoError),
+    /// The `BM` magic bytes are missing, so this is not a bitmap.
+    NotBitmap,
+    /// The bits-per-pixel value is one we do not know how to decode.
+    UnsupportedBitDepth(u16),
+    /// The input was truncated or did not parse (a header, the color table
+    /// or the pixel data), or its compression type is not one we decode.
+    MalformedHeader,
+    /// A pixel coordinate fell outside the image bounds, or a palette index
+    /// fell outside the color table.
+    IndexOutOfBounds
+}
+
+/// Shorthand for the crate's fallible operations, threaded through every
+/// parse step the way Maraiah's `ResultS` is.
+pub type BmpResult<T> = Result<T, BmpError>;
+
+/// Checked little-endian accessors over a raw byte slice, in the spirit of
+/// Maraiah's `BinUtil`. Each reader slices the requested width at `i` and
+/// assembles it little-endian, returning `MalformedHeader` when the slice
+/// does not reach that far.
+pub trait BinUtil {
+    fn c_u8(&self, i: uint) -> BmpResult<u8>;
+    fn c_u16l(&self, i: uint) -> BmpResult<u16>;
+    fn c_u32l(&self, i: uint) -> BmpResult<u32>;
+    fn c_i32l(&self, i: uint) -> BmpResult<i32>;
+}
+
+impl BinUtil for [u8] {
+    fn c_u8(&self, i: uint) -> BmpResult<u8> {
+        if i < self.len() { Ok(self[i]) } else { Err(MalformedHeader) }
+    }
+
+    fn c_u16l(&self, i: uint) -> BmpResult<u16> {
+        if i + 2 <= self.len() {
+            Ok(self[i] as u16 | (self[i + 1] as u16 << 8))
+        } else {
+            Err(MalformedHeader)
+        }
+    }
+
+    fn c_u32l(&self, i: uint) -> BmpResult<u32> {
+        if i + 4 <= self.len() {
+            Ok(self[i] as u32
+               | (self[i + 1] as u32 << 8)
+               | (self[i + 2] as u32 << 16)
+               | (self[i + 3] as u32 << 24))
+        } else {
+            Err(MalformedHeader)
+        }
+    }
+
+    fn c_i32l(&self, i: uint) -> BmpResult<i32> {
+        self.c_u32l(i).map(|v| v as i32)
+    }
+}
+
 #[deriving(Show)]
 struct BmpId {
     magic1: u8,
@@ -109,61 +169,80 @@ impl Image {
         }
     }
 
-    pub fn set_pixel(&mut self, x: uint, y: uint, val: Pixel) {
+    /// Store `val` at `(x, y)`, returning `IndexOutOfBounds` when the
+    /// coordinate or the backing data does not hold.
+    pub fn set_pixel_checked(&mut self, x: uint, y: uint, val: Pixel) -> BmpResult<()> {
         if x < self.width as uint && y < self.height as uint {
-            let data = match self.data {
-                Some(ref mut data) => data.as_mut_slice(),
-                None => fail!("Image has no data")
-            };
-            data[y * (self.width as uint) + x] = val;
+            let width = self.width as uint;
+            match self.data {
+                Some(ref mut data) => {
+                    *data.get_mut(y * width + x) = val;
+                    Ok(())
+                },
+                None => Err(IndexOutOfBounds)
+            }
         } else {
-            fail!("Index out of bounds: ({}, {})", x, y);
+            Err(IndexOutOfBounds)
         }
     }
 
-    pub fn get_pixel(&self, x: uint, y: uint) -> Pixel {
+    /// Fetch the pixel at `(x, y)`, returning `IndexOutOfBounds` when the
+    /// coordinate or the backing data does not hold.
+    pub fn get_pixel_checked(&self, x: uint, y: uint) -> BmpResult<Pixel> {
         if x < self.width as uint && y < self.height as uint {
             match self.data {
-                Some(ref data) => data[y * (self.width as uint) + x],
-                None => fail!("Image has no data")
+                Some(ref data) => Ok(data[y * (self.width as uint) + x]),
+                None => Err(IndexOutOfBounds)
             }
         } else {
-            fail!("Index out of bounds: ({}, {})", x, y);
+            Err(IndexOutOfBounds)
         }
     }
 
-    fn write_header(&self, name: &str) {
+    pub fn set_pixel(&mut self, x: uint, y: uint, val: Pixel) {
+        match self.set_pixel_checked(x, y, val) {
+            Ok(()) => (),
+            Err(e) => fail!("{}", e)
+        }
+    }
+
+    pub fn get_pixel(&self, x: uint, y: uint) -> Pixel {
+        match self.get_pixel_checked(x, y) {
+            Ok(p) => p,
+            Err(e) => fail!("{}", e)
+        }
+    }
+
+    fn write_header(&self, name: &str) -> BmpResult<()> {
         let mut f = File::create(&Path::new(name));
         let id = self.magic;
-        access(f.write([id.magic1, id.magic2]));
+        try!(f.write([id.magic1, id.magic2]).map_err(Io));
 
         let header = self.header;
-        access(f.write_le_u32(header.file_size));
-        access(f.write_le_u16(header.creator1));
-        access(f.write_le_u16(header.creator2));
-        access(f.write_le_u32(header.pixel_offset));
+        try!(f.write_le_u32(header.file_size).map_err(Io));
+        try!(f.write_le_u16(header.creator1).map_err(Io));
+        try!(f.write_le_u16(header.creator2).map_err(Io));
+        try!(f.write_le_u32(header.pixel_offset).map_err(Io));
 
         let dib_header = self.dib_header;
-        access(f.write_le_u32(dib_header.header_size));
-        access(f.write_le_i32(dib_header.width));
-        access(f.write_le_i32(dib_header.height));
-        access(f.write_le_u16(dib_header.num_planes));
-        access(f.write_le_u16(dib_header.bits_per_pixel));
-        access(f.write_le_u32(dib_header.compress_type));
-        access(f.write_le_u32(dib_header.data_size));
-        access(f.write_le_i32(dib_header.hres));
-        access(f.write_le_i32(dib_header.vres));
-        access(f.write_le_u32(dib_header.num_colors));
-        access(f.write_le_u32(dib_header.num_imp_colors));
-    }
-
-    pub fn save(&self, name: &str) {
-        self.write_header(name);
-
-        let mut file = match File::open_mode(&Path::new(name), Append, ReadWrite) {
-            Ok(f) => f,
-            Err(e) => fail!("File error: {}", e),
-        };
+        try!(f.write_le_u32(dib_header.header_size).map_err(Io));
+        try!(f.write_le_i32(dib_header.width).map_err(Io));
+        try!(f.write_le_i32(dib_header.height).map_err(Io));
+        try!(f.write_le_u16(dib_header.num_planes).map_err(Io));
+        try!(f.write_le_u16(dib_header.bits_per_pixel).map_err(Io));
+        try!(f.write_le_u32(dib_header.compress_type).map_err(Io));
+        try!(f.write_le_u32(dib_header.data_size).map_err(Io));
+        try!(f.write_le_i32(dib_header.hres).map_err(Io));
+        try!(f.write_le_i32(dib_header.vres).map_err(Io));
+        try!(f.write_le_u32(dib_header.num_colors).map_err(Io));
+        try!(f.write_le_u32(dib_header.num_imp_colors).map_err(Io));
+        Ok(())
+    }
+
+    pub fn save(&self, name: &str) -> BmpResult<()> {
+        try!(self.write_header(name));
+
+        let mut file = try!(File::open_mode(&Path::new(name), Append, ReadWrite).map_err(Io));
 
         match self.data {
             Some(ref data) => {
@@ -171,116 +250,276 @@ impl Image {
                     for x in range(0, self.width) {
                         let index: uint = (y * self.width + x) as uint;
                         let p = data[index as uint];
-                        access(file.write([p.b, p.g, p.r]));
+                        try!(file.write([p.b, p.g, p.r]).map_err(Io));
                     }
                     let p = self.padding_data.slice(0, self.padding as uint);
-                    access(file.write(p));
+                    try!(file.write(p).map_err(Io));
                 }
+                Ok(())
             },
-            None => fail!("Image has no data")
+            None => Err(MalformedHeader)
         }
     }
 
-    fn read_bmp_id(f: &mut File) -> Option<BmpId> {
-        match f.eof() {
-            false =>
-                Some(BmpId {
-                    magic1: access(f.read_byte()),
-                    magic2: access(f.read_byte())
-                }),
-            true => None
+    fn read_bmp_id(data: &[u8]) -> BmpResult<BmpId> {
+        let id = BmpId {
+            magic1: try!(data.c_u8(0)),
+            magic2: try!(data.c_u8(1))
+        };
+        if id.magic1 == 0x42 && id.magic2 == 0x4D {
+            Ok(id)
+        } else {
+            Err(NotBitmap)
         }
     }
 
-    fn read_bmp_header(f: &mut File) -> Option<BmpHeader> {
-        match f.eof() {
-            false =>
-                Some(BmpHeader {
-                    file_size: access(f.read_le_u32()),
-                    creator1: access(f.read_le_u16()),
-                    creator2: access(f.read_le_u16()),
-                    pixel_offset: access(f.read_le_u32())
-                }),
-            true => None
-        }
+    fn read_bmp_header(data: &[u8]) -> BmpResult<BmpHeader> {
+        Ok(BmpHeader {
+            file_size: try!(data.c_u32l(2)),
+            creator1: try!(data.c_u16l(6)),
+            creator2: try!(data.c_u16l(8)),
+            pixel_offset: try!(data.c_u32l(10))
+        })
     }
 
-    fn read_bmp_dib_header(f: &mut File) -> Option<BmpDibHeader> {
-        match f.eof() {
-            false =>
-                Some(BmpDibHeader {
-                    header_size: access(f.read_le_u32()),
-                    width: access(f.read_le_i32()),
-                    height: access(f.read_le_i32()),
-                    num_planes: access(f.read_le_u16()),
-                    bits_per_pixel: access(f.read_le_u16()),
-                    compress_type: access(f.read_le_u32()),
-                    data_size: access(f.read_le_u32()),
-                    hres: access(f.read_le_i32()),
-                    vres: access(f.read_le_i32()),
-                    num_colors: access(f.read_le_u32()),
-                    num_imp_colors: access(f.read_le_u32()),
-                }),
-            true => None
-        }
+    fn read_bmp_dib_header(data: &[u8]) -> BmpResult<BmpDibHeader> {
+        Ok(BmpDibHeader {
+            header_size: try!(data.c_u32l(14)),
+            width: try!(data.c_i32l(18)),
+            height: try!(data.c_i32l(22)),
+            num_planes: try!(data.c_u16l(26)),
+            bits_per_pixel: try!(data.c_u16l(28)),
+            compress_type: try!(data.c_u32l(30)),
+            data_size: try!(data.c_u32l(34)),
+            hres: try!(data.c_i32l(38)),
+            vres: try!(data.c_i32l(42)),
+            num_colors: try!(data.c_u32l(46)),
+            num_imp_colors: try!(data.c_u32l(50)),
+        })
     }
 
-    fn read_pixel(f: &mut File) -> Pixel {
-        let [b, g, r] = [
-            access(f.read_byte()),
-            access(f.read_byte()),
-            access(f.read_byte())
-        ];
-        Pixel{r: r, g: g, b: b}
+    /// Read the `RGBQUAD` color table that sits between the DIB header and
+    /// the pixel array, starting right after the whole DIB header
+    /// (`header_size` bytes, so past any V4/V5 fields). Each entry is
+    /// stored blue, green, red, reserved.
+    fn read_color_table(data: &[u8], dh: BmpDibHeader) -> BmpResult<Vec<Pixel>> {
+        let num_colors = if dh.num_colors == 0 {
+            1u32 << (dh.bits_per_pixel as uint)
+        } else {
+            dh.num_colors
+        };
+        let start = (14 + dh.header_size) as uint;
+
+        let mut table = Vec::with_capacity(num_colors as uint);
+        for i in range(0, num_colors as uint) {
+            let o = start + i * 4;
+            let b = try!(data.c_u8(o));
+            let g = try!(data.c_u8(o + 1));
+            let r = try!(data.c_u8(o + 2));
+            table.push(Pixel{r: r, g: g, b: b});
+        }
+        Ok(table)
     }
 
-    fn read_image_data(f: &mut File, dh: BmpDibHeader, offset: u32, padding: i64) -> Option<Vec<Pixel>> {
-        let data_size = ((24.0 * dh.width as f32 + 31.0) / 32.0).floor() as u32
-            * 4 * dh.height as u32;
-
-        if data_size == dh.data_size {
-            let mut data = Vec::new();
-            // seek until data
-            access(f.seek(offset as i64, SeekSet));
-            // read pixels until padding
-            for _ in range(0, dh.height) {
-                for _ in range(0, dh.width) {
-                   data.push(Image::read_pixel(f));
+    /// Decode a palettized (1/4/8 bpp) pixel array into `Pixel`s. Indices
+    /// are packed `bpp` bits per pixel, most-significant-bit first, and each
+    /// row is padded out to a four-byte boundary.
+    fn read_indexed_data(data: &[u8], dh: BmpDibHeader, offset: u32,
+                         table: &[Pixel]) -> BmpResult<Vec<Pixel>> {
+        let bpp = dh.bits_per_pixel as uint;
+        let width = dh.width as uint;
+        let height = dh.height as uint;
+        let row_size = (((bpp * width) + 31) / 32) * 4;
+        let mask = (1u << bpp) - 1;
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in range(0, height) {
+            let row_start = offset as uint + y * row_size;
+            for x in range(0, width) {
+                let bit = x * bpp;
+                let byte = try!(data.c_u8(row_start + bit / 8)) as uint;
+                let shift = 8 - bpp - (bit % 8);
+                let index = (byte >> shift) & mask;
+                if index >= table.len() {
+                    return Err(IndexOutOfBounds);
                 }
-                // seek padding
-                access(f.seek(padding, SeekCur));
+                pixels.push(table[index]);
             }
-            Some(data)
-        } else {
-            None
         }
+        Ok(pixels)
     }
 
-    pub fn open(name: &str) -> Image {
-        let mut f = match File::open_mode(&Path::new(name), Open, Read) {
-            Ok(f) => f,
-            Err(e) => fail!("File error: {}", e),
-        };
+    /// Decode a run-length-encoded palettized pixel array (BI_RLE8 when
+    /// `rle4` is false, BI_RLE4 when true) into `Pixel`s. The stream is a
+    /// sequence of byte pairs: a nonzero count is a run of one index (two
+    /// alternating 4-bit indices for RLE4), a zero count introduces an
+    /// escape — end of line, end of bitmap, a delta jump, or an
+    /// absolute run of literal indices padded to a 16-bit boundary.
+    fn read_rle_data(data: &[u8], dh: BmpDibHeader, offset: u32,
+                     table: &[Pixel], rle4: bool) -> BmpResult<Vec<Pixel>> {
+        let width = dh.width as uint;
+        let height = dh.height as uint;
+
+        let mut pos = offset as uint;
+        let mut indices = Vec::from_elem(width * height, 0u8);
+        let mut x = 0u;
+        let mut y = 0u;
+        loop {
+            let n = try!(data.c_u8(pos)) as uint;
+            pos += 1;
+            if n != 0 {
+                let val = try!(data.c_u8(pos));
+                pos += 1;
+                for i in range(0, n) {
+                    let index = if rle4 {
+                        if i % 2 == 0 { val >> 4 } else { val & 0x0f }
+                    } else {
+                        val
+                    };
+                    if x < width && y < height {
+                        *indices.get_mut(y * width + x) = index;
+                    }
+                    x += 1;
+                }
+            } else {
+                let escape = try!(data.c_u8(pos)) as uint;
+                pos += 1;
+                match escape {
+                    0 => { x = 0; y += 1; },
+                    1 => break,
+                    2 => {
+                        let dx = try!(data.c_u8(pos)) as uint;
+                        let dy = try!(data.c_u8(pos + 1)) as uint;
+                        pos += 2;
+                        x += dx;
+                        y += dy;
+                    },
+                    k => {
+                        // Absolute mode: k literal indices follow.
+                        let mut packed = 0u8;
+                        for i in range(0, k) {
+                            let index = if rle4 {
+                                if i % 2 == 0 {
+                                    packed = try!(data.c_u8(pos));
+                                    pos += 1;
+                                    packed >> 4
+                                } else {
+                                    packed & 0x0f
+                                }
+                            } else {
+                                let b = try!(data.c_u8(pos));
+                                pos += 1;
+                                b
+                            };
+                            if x < width && y < height {
+                                *indices.get_mut(y * width + x) = index;
+                            }
+                            x += 1;
+                        }
+                        // The literal run is padded out to a 16-bit word.
+                        let bytes = if rle4 { (k + 1) / 2 } else { k };
+                        if bytes % 2 == 1 {
+                            pos += 1;
+                        }
+                    }
+                }
+            }
+        }
 
-        let id = match Image::read_bmp_id(&mut f) {
-            Some(id) => id,
-            None => fail!("File is not a bitmap")
-        };
-        assert_eq!(id.magic1, 0x42);
-        assert_eq!(id.magic2, 0x4D);
+        let mut pixels = Vec::with_capacity(width * height);
+        for &index in indices.iter() {
+            let index = index as uint;
+            if index >= table.len() {
+                return Err(IndexOutOfBounds);
+            }
+            pixels.push(table[index]);
+        }
+        Ok(pixels)
+    }
 
-        let header = match Image::read_bmp_header(&mut f) {
-            Some(header) => header,
-            None => fail!("Header of bitmap is not valid")
-        };
+    /// Resolve the red/green/blue channel masks for a 16- or 32-bit image.
+    /// For `BI_BITFIELDS` the four channel masks follow the core header
+    /// fields (BITMAPV4HEADER and later store them there too); otherwise a
+    /// 16-bit image defaults to the standard 5-5-5 layout and a 32-bit one
+    /// to 8-8-8.
+    fn read_masks(data: &[u8], dh: BmpDibHeader) -> BmpResult<(u32, u32, u32)> {
+        if dh.compress_type == 3 {
+            Ok((try!(data.c_u32l(54)), try!(data.c_u32l(58)), try!(data.c_u32l(62))))
+        } else if dh.bits_per_pixel == 16 {
+            Ok((0x7c00, 0x03e0, 0x001f))
+        } else {
+            Ok((0x00ff0000, 0x0000ff00, 0x000000ff))
+        }
+    }
 
-        let dib_header = match Image::read_bmp_dib_header(&mut f) {
-            Some(dib_header) => dib_header,
-            None => fail!("DIB header of bitmap is not valid")
-        };
+    fn read_image_data(data: &[u8], dh: BmpDibHeader, offset: u32) -> BmpResult<Vec<Pixel>> {
+        match dh.bits_per_pixel {
+            1 | 4 | 8 => {
+                let table = try!(Image::read_color_table(data, dh));
+                match dh.compress_type {
+                    0 => Image::read_indexed_data(data, dh, offset, table.as_slice()),
+                    1 => Image::read_rle_data(data, dh, offset, table.as_slice(), false),
+                    2 => Image::read_rle_data(data, dh, offset, table.as_slice(), true),
+                    _ => Err(MalformedHeader)
+                }
+            },
+            24 => {
+                let width = dh.width as uint;
+                let height = dh.height as uint;
+                let row_size = (((24 * width) + 31) / 32) * 4;
+
+                let mut pixels = Vec::with_capacity(width * height);
+                for y in range(0, height) {
+                    let row_start = offset as uint + y * row_size;
+                    for x in range(0, width) {
+                        let o = row_start + x * 3;
+                        let b = try!(data.c_u8(o));
+                        let g = try!(data.c_u8(o + 1));
+                        let r = try!(data.c_u8(o + 2));
+                        pixels.push(Pixel{r: r, g: g, b: b});
+                    }
+                }
+                Ok(pixels)
+            },
+            16 | 32 => {
+                let bpp = dh.bits_per_pixel as uint;
+                let width = dh.width as uint;
+                let height = dh.height as uint;
+                let (r_mask, g_mask, b_mask) = try!(Image::read_masks(data, dh));
+                let row_size = (((bpp * width) + 31) / 32) * 4;
+
+                let mut pixels = Vec::with_capacity(width * height);
+                for y in range(0, height) {
+                    let row_start = offset as uint + y * row_size;
+                    for x in range(0, width) {
+                        let o = row_start + x * (bpp / 8);
+                        let value = if bpp == 16 {
+                            try!(data.c_u16l(o)) as u32
+                        } else {
+                            try!(data.c_u32l(o))
+                        };
+                        pixels.push(Pixel {
+                            r: scale_channel(value, r_mask),
+                            g: scale_channel(value, g_mask),
+                            b: scale_channel(value, b_mask)
+                        });
+                    }
+                }
+                Ok(pixels)
+            },
+            bpp => Err(UnsupportedBitDepth(bpp))
+        }
+    }
+
+    /// Decode a bitmap that is already resident in memory.
+    pub fn from_bytes(bytes: &[u8]) -> BmpResult<Image> {
+        let id = try!(Image::read_bmp_id(bytes));
+        let header = try!(Image::read_bmp_header(bytes));
+        let dib_header = try!(Image::read_bmp_dib_header(bytes));
 
         let padding = dib_header.width % 4;
-        Image {
+        let data = try!(Image::read_image_data(bytes, dib_header, header.pixel_offset));
+        Ok(Image {
             magic: id,
             header: header,
             dib_header: dib_header,
@@ -288,15 +527,86 @@ impl Image {
             height: dib_header.height,
             padding: padding,
             padding_data: [0, 0, 0, 0],
-            data: Image::read_image_data(&mut f, dib_header, header.pixel_offset, padding as i64)
+            data: Some(data)
+        })
+    }
+
+    pub fn open(name: &str) -> BmpResult<Image> {
+        let mut f = try!(File::open_mode(&Path::new(name), Open, Read).map_err(Io));
+        let bytes = try!(f.read_to_end().map_err(Io));
+        Image::from_bytes(bytes.as_slice())
+    }
+
+    /// Iterate over every `(x, y)` coordinate in row-major order, so callers
+    /// can fill an image without nesting `range` loops by hand.
+    pub fn coordinates(&self) -> Coordinates {
+        Coordinates {
+            width: self.width as uint,
+            height: self.height as uint,
+            x: 0,
+            y: 0
+        }
+    }
+}
+
+impl Index<(uint, uint), Pixel> for Image {
+    fn index<'a>(&'a self, &(x, y): &(uint, uint)) -> &'a Pixel {
+        match self.data {
+            Some(ref data) => &data[y * (self.width as uint) + x],
+            None => fail!("Image has no data")
+        }
+    }
+}
+
+impl IndexMut<(uint, uint), Pixel> for Image {
+    fn index_mut<'a>(&'a mut self, &(x, y): &(uint, uint)) -> &'a mut Pixel {
+        let width = self.width as uint;
+        match self.data {
+            Some(ref mut data) => data.get_mut(y * width + x),
+            None => fail!("Image has no data")
+        }
+    }
+}
+
+/// Row-major iterator over an image's pixel coordinates, yielded as
+/// `(x, y)` pairs. Produced by `Image::coordinates`.
+pub struct Coordinates {
+    width: uint,
+    height: uint,
+    x: uint,
+    y: uint
+}
+
+impl Iterator<(uint, uint)> for Coordinates {
+    fn next(&mut self) -> Option<(uint, uint)> {
+        if self.width == 0 || self.y >= self.height {
+            return None;
+        }
+        let coord = (self.x, self.y);
+        self.x += 1;
+        if self.x >= self.width {
+            self.x = 0;
+            self.y += 1;
         }
+        Some(coord)
     }
 }
 
-fn access<T>(res: IoResult<T>) -> T {
-    match res {
-        Err(e) => fail!("File error: {}", e),
-        Ok(r) => r
+/// Pull a single channel out of a packed pixel value using its bit mask and
+/// scale it to 8 bits by shifting: narrower channels are shifted left (a
+/// 5-bit maximum of 31 becomes 248) and wider ones right. The shift is the
+/// mask's trailing zero count and the channel width is its population count.
+fn scale_channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let width = mask.count_ones();
+    let channel = (value & mask) >> shift;
+    if width >= 8 {
+        (channel >> (width - 8)) as u8
+    } else {
+        (channel << (8 - width)) as u8
     }
 }
 
@@ -356,7 +666,7 @@ mod tests {
 
     #[test]
     fn can_read_bmp_image() {
-        let bmp_img = Image::open("src/test/rgbw.bmp");
+        let bmp_img = Image::open("src/test/rgbw.bmp").unwrap();
         verify_test_bmp_image(bmp_img);
     }
 
@@ -385,7 +695,7 @@ mod tests {
 
     #[test]
     fn can_read_entire_bmp_image() {
-        let bmp_img = Image::open("src/test/rgbw.bmp");
+        let bmp_img = Image::open("src/test/rgbw.bmp").unwrap();
         assert!(None != bmp_img.data);
 
         assert_eq!(bmp_img.get_pixel(0, 0), BLUE);
@@ -401,9 +711,9 @@ mod tests {
         bmp.set_pixel(1, 0, WHITE);
         bmp.set_pixel(0, 1, BLUE);
         bmp.set_pixel(1, 1, LIME);
-        bmp.save("src/test/rgbw_test.bmp");
+        bmp.save("src/test/rgbw_test.bmp").unwrap();
 
-        let bmp_img = Image::open("src/test/rgbw_test.bmp");
+        let bmp_img = Image::open("src/test/rgbw_test.bmp").unwrap();
         assert_eq!(bmp_img.get_pixel(0, 0), RED);
         assert_eq!(bmp_img.get_pixel(1, 0), WHITE);
         assert_eq!(bmp_img.get_pixel(0, 1), BLUE);